@@ -0,0 +1,563 @@
+//! Stream decoders.
+
+use std::{
+    error::Error as StdError,
+    future::Future,
+    io::{self, Write as _},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_rt::task::{spawn_blocking, JoinHandle};
+use brotli2::write::BrotliDecoder;
+use bytes::{Bytes, BytesMut};
+use derive_more::Display;
+use flate2::write::{GzDecoder, ZlibDecoder};
+use futures_core::ready;
+use pin_project::pin_project;
+use zstd::stream::write::Decoder as ZstdDecoder;
+
+use crate::{
+    body::{BodySize, MessageBody},
+    error::{BlockingError, PayloadError},
+    http::header::{ContentEncoding, HeaderMap, CONTENT_ENCODING},
+    Error,
+};
+
+const MAX_CHUNK_SIZE_DECODE_IN_PLACE: usize = 1024;
+
+/// Decompresses a request body, mirroring [`Encoder`](super::Encoder) in reverse.
+#[pin_project]
+pub struct Decoder<B> {
+    #[pin]
+    body: B,
+    stack: Option<DecoderStack>,
+    fut: Option<JoinHandle<Result<DecoderStack, io::Error>>>,
+    eof: bool,
+}
+
+impl<B> Decoder<B>
+where
+    B: MessageBody,
+    B::Error: Into<Error>,
+{
+    /// Wraps `body`, inflating it according to the `Content-Encoding` header in `headers`.
+    ///
+    /// Chained encodings (e.g. `Content-Encoding: gzip, br`) are undone in reverse of the
+    /// order they're listed in the header, since that's the order they were applied in.
+    /// `max_size` caps the number of decompressed bytes any stage of the chain is allowed to
+    /// produce; once exceeded, the stream yields [`DecoderError::Overflow`] instead of
+    /// continuing to inflate a potential zip-bomb payload. The cap is enforced as each stage
+    /// writes its output, not only after a whole input chunk has finished decompressing, so a
+    /// high-ratio payload is cut off as soon as it overshoots rather than being fully
+    /// materialized in memory first.
+    pub fn new(headers: &HeaderMap, body: B, max_size: usize) -> Decoder<B> {
+        let stages = headers
+            .get(&CONTENT_ENCODING)
+            .and_then(|val| val.to_str().ok())
+            .map(|value| {
+                let codings: Vec<ContentEncoding> = value
+                    .split(',')
+                    .filter_map(content_encoding_from_str)
+                    .filter(|encoding| *encoding != ContentEncoding::Identity)
+                    .collect();
+
+                codings
+                    .into_iter()
+                    .rev()
+                    .filter_map(|encoding| ContentDecoder::new(encoding, max_size))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        Decoder {
+            body,
+            stack: if stages.is_empty() {
+                None
+            } else {
+                Some(DecoderStack::new(stages))
+            },
+            fut: None,
+            eof: false,
+        }
+    }
+}
+
+fn content_encoding_from_str(coding: &str) -> Option<ContentEncoding> {
+    match coding.trim().to_ascii_lowercase().as_str() {
+        "gzip" => Some(ContentEncoding::Gzip),
+        "deflate" => Some(ContentEncoding::Deflate),
+        "br" => Some(ContentEncoding::Br),
+        "zstd" => Some(ContentEncoding::Zstd),
+        "identity" => Some(ContentEncoding::Identity),
+        _ => None,
+    }
+}
+
+/// Turns the sentinel IO error raised by [`CappedWriter`] into [`DecoderError::Overflow`],
+/// passing any other IO error through unchanged.
+fn map_write_err<E>(err: io::Error) -> DecoderError<E> {
+    match err.get_ref() {
+        Some(inner) if inner.is::<SizeLimitExceeded>() => DecoderError::Overflow,
+        _ => DecoderError::Io(err),
+    }
+}
+
+impl<B> MessageBody for Decoder<B>
+where
+    B: MessageBody,
+    B::Error: Into<Error>,
+{
+    type Error = DecoderError<B::Error>;
+
+    fn size(&self) -> BodySize {
+        if self.stack.is_none() {
+            self.body.size()
+        } else {
+            BodySize::Stream
+        }
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let mut this = self.project();
+        loop {
+            if *this.eof {
+                return Poll::Ready(None);
+            }
+
+            if let Some(ref mut fut) = this.fut {
+                let mut stack = ready!(Pin::new(fut).poll(cx))
+                    .map_err(|_| DecoderError::Blocking(BlockingError))?
+                    .map_err(map_write_err)?;
+
+                let chunk = stack.take();
+                *this.stack = Some(stack);
+                this.fut.take();
+
+                if !chunk.is_empty() {
+                    return Poll::Ready(Some(Ok(chunk)));
+                }
+            }
+
+            let result = ready!(this.body.as_mut().poll_next(cx));
+
+            match result {
+                Some(Err(err)) => return Poll::Ready(Some(Err(DecoderError::Body(err)))),
+
+                Some(Ok(chunk)) => {
+                    if let Some(mut stack) = this.stack.take() {
+                        if chunk.len() < MAX_CHUNK_SIZE_DECODE_IN_PLACE {
+                            stack.write(&chunk).map_err(map_write_err)?;
+                            let chunk = stack.take();
+                            *this.stack = Some(stack);
+
+                            if !chunk.is_empty() {
+                                return Poll::Ready(Some(Ok(chunk)));
+                            }
+                        } else {
+                            *this.fut = Some(spawn_blocking(move || {
+                                stack.write(&chunk)?;
+                                Ok(stack)
+                            }));
+                        }
+                    } else {
+                        return Poll::Ready(Some(Ok(chunk)));
+                    }
+                }
+
+                None => {
+                    if let Some(stack) = this.stack.take() {
+                        let chunk = stack.finish().map_err(map_write_err)?;
+                        if chunk.is_empty() {
+                            return Poll::Ready(None);
+                        } else {
+                            *this.eof = true;
+                            return Poll::Ready(Some(Ok(chunk)));
+                        }
+                    } else {
+                        return Poll::Ready(None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A chain of [`ContentDecoder`]s to undo chained `Content-Encoding`s, exposing the same
+/// `write`/`take`/`finish` shape as a single codec so [`Decoder::poll_next`] can stay a plain
+/// mirror of [`Encoder::poll_next`](super::Encoder).
+struct DecoderStack {
+    stages: Vec<ContentDecoder>,
+    pending: Bytes,
+}
+
+impl DecoderStack {
+    fn new(stages: Vec<ContentDecoder>) -> Self {
+        DecoderStack {
+            stages,
+            pending: Bytes::new(),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        let mut current = Bytes::copy_from_slice(data);
+
+        for stage in self.stages.iter_mut() {
+            if current.is_empty() {
+                return Ok(());
+            }
+
+            stage.write(&current)?;
+            current = stage.take();
+        }
+
+        if !current.is_empty() {
+            let mut buf = BytesMut::from(&self.pending[..]);
+            buf.extend_from_slice(&current);
+            self.pending = buf.freeze();
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn take(&mut self) -> Bytes {
+        std::mem::take(&mut self.pending)
+    }
+
+    fn finish(self) -> Result<Bytes, io::Error> {
+        // `pending` already holds fully-decoded bytes produced while streaming. Anything
+        // still buffered inside a stage now needs to be flushed through the remainder of
+        // the chain before it counts as output.
+        let mut output = BytesMut::from(&self.pending[..]);
+
+        let mut carry = Bytes::new();
+        for mut stage in self.stages {
+            if !carry.is_empty() {
+                stage.write(&carry)?;
+                carry = stage.take();
+            }
+
+            let flushed = stage.finish()?;
+            carry = if carry.is_empty() {
+                flushed
+            } else {
+                let mut combined = BytesMut::from(&carry[..]);
+                combined.extend_from_slice(&flushed);
+                combined.freeze()
+            };
+        }
+
+        output.extend_from_slice(&carry);
+        Ok(output.freeze())
+    }
+}
+
+enum ContentDecoder {
+    Deflate(ZlibDecoder<CappedWriter>),
+    Gzip(GzDecoder<CappedWriter>),
+    Br(BrotliDecoder<CappedWriter>),
+    Zstd(ZstdDecoder<'static, CappedWriter>),
+}
+
+impl ContentDecoder {
+    fn new(encoding: ContentEncoding, max_size: usize) -> Option<Self> {
+        match encoding {
+            ContentEncoding::Deflate => Some(ContentDecoder::Deflate(ZlibDecoder::new(
+                CappedWriter::new(max_size),
+            ))),
+            ContentEncoding::Gzip => Some(ContentDecoder::Gzip(GzDecoder::new(
+                CappedWriter::new(max_size),
+            ))),
+            ContentEncoding::Br => Some(ContentDecoder::Br(BrotliDecoder::new(
+                CappedWriter::new(max_size),
+            ))),
+            ContentEncoding::Zstd => {
+                let decoder = ZstdDecoder::new(CappedWriter::new(max_size)).ok()?;
+                Some(ContentDecoder::Zstd(decoder))
+            }
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn take(&mut self) -> Bytes {
+        match *self {
+            ContentDecoder::Br(ref mut decoder) => decoder.get_mut().take(),
+            ContentDecoder::Deflate(ref mut decoder) => decoder.get_mut().take(),
+            ContentDecoder::Gzip(ref mut decoder) => decoder.get_mut().take(),
+            ContentDecoder::Zstd(ref mut decoder) => decoder.get_mut().take(),
+        }
+    }
+
+    fn finish(self) -> Result<Bytes, io::Error> {
+        match self {
+            ContentDecoder::Br(decoder) => Ok(decoder.finish()?.take()),
+            ContentDecoder::Gzip(decoder) => Ok(decoder.finish()?.take()),
+            ContentDecoder::Deflate(decoder) => Ok(decoder.finish()?.take()),
+            ContentDecoder::Zstd(decoder) => Ok(decoder.finish()?.take()),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        match *self {
+            ContentDecoder::Br(ref mut decoder) => decoder.write_all(data),
+            ContentDecoder::Gzip(ref mut decoder) => decoder.write_all(data),
+            ContentDecoder::Deflate(ref mut decoder) => decoder.write_all(data),
+            ContentDecoder::Zstd(ref mut decoder) => decoder.write_all(data),
+        }
+    }
+}
+
+/// Marker error stashed inside the [`io::Error`] returned by [`CappedWriter::write`] once the
+/// cap is crossed, so callers can tell "the payload is too big" apart from a genuine IO
+/// failure without string-matching the message.
+#[derive(Debug)]
+struct SizeLimitExceeded;
+
+impl std::fmt::Display for SizeLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("decompressed payload exceeded the configured size limit")
+    }
+}
+
+impl StdError for SizeLimitExceeded {}
+
+/// An `io::Write` sink that every codec's write-based decoder flushes its output into.
+///
+/// Crucially, flate2/brotli2/zstd's write-based decoders call `write` on their inner sink
+/// repeatedly *while* decompressing a single input chunk, not just once at the end — so
+/// capping here, rather than only checking the total size after a whole chunk has been
+/// decompressed, stops a high-ratio payload as soon as its output crosses `max_size` instead
+/// of after it has already been fully materialized in memory.
+struct CappedWriter {
+    buf: BytesMut,
+    max_size: usize,
+    produced: usize,
+}
+
+impl CappedWriter {
+    fn new(max_size: usize) -> Self {
+        CappedWriter {
+            buf: BytesMut::new(),
+            max_size,
+            produced: 0,
+        }
+    }
+
+    #[inline]
+    fn take(&mut self) -> Bytes {
+        std::mem::take(&mut self.buf).freeze()
+    }
+}
+
+impl io::Write for CappedWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.produced += data.len();
+        if self.produced > self.max_size {
+            return Err(io::Error::new(io::ErrorKind::Other, SizeLimitExceeded));
+        }
+
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Display)]
+#[non_exhaustive]
+pub enum DecoderError<E> {
+    #[display(fmt = "body")]
+    Body(E),
+
+    #[display(fmt = "blocking")]
+    Blocking(BlockingError),
+
+    #[display(fmt = "io")]
+    Io(io::Error),
+
+    /// Decompressing the payload would have exceeded the configured size limit.
+    #[display(fmt = "size limit exceeded while decompressing payload")]
+    Overflow,
+}
+
+impl<E: StdError> StdError for DecoderError<E> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+}
+
+impl<E: Into<Error>> From<DecoderError<E>> for Error {
+    fn from(err: DecoderError<E>) -> Self {
+        match err {
+            DecoderError::Body(err) => err.into(),
+            DecoderError::Blocking(err) => err.into(),
+            DecoderError::Io(err) => err.into(),
+            DecoderError::Overflow => PayloadError::Overflow.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use brotli2::write::BrotliEncoder;
+    use flate2::write::GzEncoder;
+
+    use super::*;
+    use crate::{
+        body::{to_bytes, Body},
+        http::header::HeaderValue,
+    };
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn brotli(data: &[u8]) -> Vec<u8> {
+        let mut encoder = BrotliEncoder::new(Vec::new(), 3);
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn headers_with_encoding(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    /// A body that yields exactly the chunks it's constructed with, one per `poll_next` —
+    /// unlike `Body::Bytes`, which always hands the whole payload to a single `poll_next`
+    /// call, this drives `Decoder` across several writes so the `spawn_blocking` branch and
+    /// `DecoderStack::finish`'s cross-stage flush are actually exercised.
+    struct ChunkedBody(VecDeque<Bytes>);
+
+    impl ChunkedBody {
+        fn new(chunks: impl IntoIterator<Item = Bytes>) -> Self {
+            ChunkedBody(chunks.into_iter().collect())
+        }
+    }
+
+    impl MessageBody for ChunkedBody {
+        type Error = Error;
+
+        fn size(&self) -> BodySize {
+            BodySize::Stream
+        }
+
+        fn poll_next(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+            Poll::Ready(self.get_mut().0.pop_front().map(Ok))
+        }
+    }
+
+    /// Non-repeating filler that flate2 can't compress away to nothing, so the gzip output
+    /// stays above `MAX_CHUNK_SIZE_DECODE_IN_PLACE` and exercises the `spawn_blocking` path.
+    fn incompressible_filler(len: usize) -> Vec<u8> {
+        let mut state: u32 = 0x9E37_79B9;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[actix_rt::test]
+    async fn round_trips_a_single_codec() {
+        let body = Body::Bytes(Bytes::from(gzip(b"hello decoder")));
+        let headers = headers_with_encoding("gzip");
+
+        let decoder = Decoder::new(&headers, body, 1024);
+        let decoded = to_bytes(decoder).await.unwrap();
+
+        assert_eq!(decoded, Bytes::from_static(b"hello decoder"));
+    }
+
+    #[actix_rt::test]
+    async fn undoes_chained_encodings_in_reverse() {
+        // `Content-Encoding: gzip, br` means gzip was applied first and br second, so
+        // decoding must undo br first, then gzip.
+        let body = Body::Bytes(Bytes::from(brotli(&gzip(b"chained payload"))));
+        let headers = headers_with_encoding("gzip, br");
+
+        let decoder = Decoder::new(&headers, body, 1024);
+        let decoded = to_bytes(decoder).await.unwrap();
+
+        assert_eq!(decoded, Bytes::from_static(b"chained payload"));
+    }
+
+    #[actix_rt::test]
+    async fn overflow_past_max_size_is_rejected() {
+        let body = Body::Bytes(Bytes::from(gzip(&[0u8; 4096])));
+        let headers = headers_with_encoding("gzip");
+
+        let decoder = Decoder::new(&headers, body, 16);
+        let err = to_bytes(decoder).await.unwrap_err();
+
+        assert!(matches!(err, DecoderError::Overflow));
+    }
+
+    #[actix_rt::test]
+    async fn overflow_is_caught_mid_chunk_not_after_full_materialization() {
+        // A single, highly compressible input chunk that would inflate to far more than
+        // `max_size` — the cap must stop the decoder while the codec is still writing
+        // output, not only once the whole chunk has finished decompressing.
+        let plaintext = vec![0u8; 1 << 20]; // 1 MiB of zeros, compresses tiny.
+        let body = Body::Bytes(Bytes::from(gzip(&plaintext)));
+        let headers = headers_with_encoding("gzip");
+
+        let decoder = Decoder::new(&headers, body, 1024);
+        let err = to_bytes(decoder).await.unwrap_err();
+
+        assert!(matches!(err, DecoderError::Overflow));
+    }
+
+    #[actix_rt::test]
+    async fn spawn_blocking_path_is_exercised_for_large_chunks() {
+        let plaintext = incompressible_filler(4096);
+        let compressed = gzip(&plaintext);
+        // A single chunk at or above `MAX_CHUNK_SIZE_DECODE_IN_PLACE` routes through
+        // `spawn_blocking` instead of decoding in place.
+        assert!(compressed.len() >= MAX_CHUNK_SIZE_DECODE_IN_PLACE);
+
+        let body = ChunkedBody::new([Bytes::from(compressed)]);
+        let headers = headers_with_encoding("gzip");
+
+        let decoder = Decoder::new(&headers, body, plaintext.len() + 1024);
+        let decoded = to_bytes(decoder).await.unwrap();
+
+        assert_eq!(decoded, Bytes::from(plaintext));
+    }
+
+    #[actix_rt::test]
+    async fn streams_multi_chunk_body_through_finish_cascade() {
+        // Split a chained gzip+brotli payload across several small writes, so the last
+        // bytes of the brotli stage are still buffered when the body ends and have to be
+        // flushed through the gzip stage by `DecoderStack::finish`.
+        let compressed = brotli(&gzip(b"streamed across many small chunks"));
+        let chunks = compressed.chunks(8).map(Bytes::copy_from_slice);
+
+        let body = ChunkedBody::new(chunks);
+        let headers = headers_with_encoding("gzip, br");
+
+        let decoder = Decoder::new(&headers, body, 1024);
+        let decoded = to_bytes(decoder).await.unwrap();
+
+        assert_eq!(
+            decoded,
+            Bytes::from_static(b"streamed across many small chunks")
+        );
+    }
+}
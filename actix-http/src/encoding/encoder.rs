@@ -20,7 +20,7 @@ use zstd::stream::write::Encoder as ZstdEncoder;
 use crate::{
     body::{Body, BodySize, BoxAnyBody, MessageBody, ResponseBody},
     http::{
-        header::{ContentEncoding, CONTENT_ENCODING},
+        header::{ContentEncoding, HeaderMap, ACCEPT_ENCODING, CONTENT_ENCODING},
         HeaderValue, StatusCode,
     },
     Error, ResponseHead,
@@ -45,6 +45,17 @@ impl<B: MessageBody> Encoder<B> {
         encoding: ContentEncoding,
         head: &mut ResponseHead,
         body: ResponseBody<B>,
+    ) -> ResponseBody<Encoder<B>> {
+        Self::with_level(encoding, CompressionLevel::default(), head, body)
+    }
+
+    /// Same as [`response`](Self::response) but allows the compression level to be tuned
+    /// per-codec instead of always using the codec's default quality.
+    pub fn with_level(
+        encoding: ContentEncoding,
+        level: CompressionLevel,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
     ) -> ResponseBody<Encoder<B>> {
         let can_encode = !(head.headers().contains_key(&CONTENT_ENCODING)
             || head.status == StatusCode::SWITCHING_PROTOCOLS
@@ -70,7 +81,7 @@ impl<B: MessageBody> Encoder<B> {
 
         if can_encode {
             // Modify response body only if encoder is not None
-            if let Some(enc) = ContentEncoder::encoder(encoding) {
+            if let Some(enc) = ContentEncoder::encoder(encoding, level) {
                 update_head(encoding, head);
                 head.no_chunking(false);
                 return ResponseBody::Body(Encoder {
@@ -227,6 +238,74 @@ where
     }
 }
 
+/// Compression quality knob for [`Encoder`].
+///
+/// Each codec has its own native quality range, so `Precise` values are clamped to whatever
+/// range the chosen codec supports rather than erroring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionLevel {
+    /// Fastest compression, at the cost of a larger output.
+    Fastest,
+
+    /// The speed every codec used before this knob existed. This is the default, so
+    /// callers that don't ask for a level see no change in behavior.
+    Default,
+
+    /// Slowest compression, for the smallest possible output.
+    Best,
+
+    /// An exact, codec-native quality level.
+    ///
+    /// Out-of-range values are clamped to the chosen codec's valid range (flate2 0-9,
+    /// Brotli 0-11, Zstd 1-22).
+    Precise(i32),
+}
+
+impl Default for CompressionLevel {
+    fn default() -> Self {
+        Self::Default
+    }
+}
+
+impl CompressionLevel {
+    fn flate2_level(self) -> flate2::Compression {
+        let level = match self {
+            Self::Fastest => 1,
+            // Matches the `flate2::Compression::fast()` this module hard-coded before
+            // levels were configurable.
+            Self::Default => 1,
+            Self::Best => 9,
+            Self::Precise(level) => level.clamp(0, 9),
+        };
+
+        flate2::Compression::new(level as u32)
+    }
+
+    fn brotli_level(self) -> u32 {
+        let level = match self {
+            Self::Fastest => 1,
+            // Matches the hard-coded Brotli quality this module used before levels were
+            // configurable.
+            Self::Default => 3,
+            Self::Best => 11,
+            Self::Precise(level) => level.clamp(0, 11),
+        };
+
+        level as u32
+    }
+
+    fn zstd_level(self) -> i32 {
+        match self {
+            Self::Fastest => 1,
+            // Matches the hard-coded Zstd level this module used before levels were
+            // configurable.
+            Self::Default => 3,
+            Self::Best => 22,
+            Self::Precise(level) => level.clamp(1, 22),
+        }
+    }
+}
+
 fn update_head(encoding: ContentEncoding, head: &mut ResponseHead) {
     head.headers_mut().insert(
         CONTENT_ENCODING,
@@ -244,21 +323,22 @@ enum ContentEncoder {
 }
 
 impl ContentEncoder {
-    fn encoder(encoding: ContentEncoding) -> Option<Self> {
+    fn encoder(encoding: ContentEncoding, level: CompressionLevel) -> Option<Self> {
         match encoding {
             ContentEncoding::Deflate => Some(ContentEncoder::Deflate(ZlibEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                level.flate2_level(),
             ))),
             ContentEncoding::Gzip => Some(ContentEncoder::Gzip(GzEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                level.flate2_level(),
+            ))),
+            ContentEncoding::Br => Some(ContentEncoder::Br(BrotliEncoder::new(
+                Writer::new(),
+                level.brotli_level(),
             ))),
-            ContentEncoding::Br => {
-                Some(ContentEncoder::Br(BrotliEncoder::new(Writer::new(), 3)))
-            }
             ContentEncoding::Zstd => {
-                let encoder = ZstdEncoder::new(Writer::new(), 3).ok()?;
+                let encoder = ZstdEncoder::new(Writer::new(), level.zstd_level()).ok()?;
                 Some(ContentEncoder::Zstd(encoder))
             }
             _ => None,
@@ -362,3 +442,155 @@ impl<E: Into<Error>> From<EncoderError<E>> for Error {
         }
     }
 }
+
+/// Resolves [`ContentEncoding::Auto`] into a concrete encoding by negotiating against the
+/// request's `Accept-Encoding` header.
+///
+/// `codecs` lists the server's compiled-in encodings in preference order (e.g.
+/// `br > zstd > gzip > deflate`); ties in q-value are broken by that order. Codings with
+/// `q=0` are treated as unacceptable, the `*` wildcard is honored, and a missing or empty
+/// header is treated as accepting anything. Returns `None` when none of `codecs` is
+/// acceptable to the client, in which case the body should be served uncompressed rather
+/// than forcing an encoding on it.
+///
+/// Note that `codecs` is never expected to contain [`ContentEncoding::Identity`], so an
+/// explicit `identity;q=0` token in the header is not specially detected or rejected here —
+/// this function can't tell "the client didn't object to identity" apart from "the client
+/// forbade identity too". A caller that must honor the latter (e.g. to respond `406` instead
+/// of silently falling back to an uncompressed body) needs to check the header for that case
+/// itself before treating `None` as "uncompressed is fine".
+pub fn negotiate_content_encoding(
+    headers: &HeaderMap,
+    codecs: &[ContentEncoding],
+) -> Option<ContentEncoding> {
+    let header = headers.get(&ACCEPT_ENCODING).and_then(|val| val.to_str().ok());
+
+    let preferences = match header {
+        Some(header) if !header.is_empty() => parse_accept_encoding(header),
+        _ => return codecs.first().copied(),
+    };
+
+    let wildcard_q = preferences
+        .iter()
+        .find(|(coding, _)| coding == "*")
+        .map(|&(_, q)| q);
+
+    codecs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &codec)| {
+            let q = preferences
+                .iter()
+                .find(|(coding, _)| coding == codec.as_str())
+                .map(|&(_, q)| q)
+                .or(wildcard_q)
+                .unwrap_or(0.0);
+
+            if q > 0.0 {
+                Some((index, codec, q))
+            } else {
+                None
+            }
+        })
+        // `max_by` returns the *last* of equally-maximal elements, so break ties by the
+        // reverse of `codecs`' index to keep the server's preference order (earlier wins).
+        .max_by(|(ia, _, qa), (ib, _, qb)| {
+            qa.partial_cmp(qb)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| ib.cmp(ia))
+        })
+        .map(|(_, codec, _)| codec)
+}
+
+/// Parses an `Accept-Encoding` header value into `(coding, q-value)` pairs, lower-casing
+/// codings and defaulting a missing `q` parameter to `1.0`.
+fn parse_accept_encoding(header: &str) -> Vec<(String, f32)> {
+    header
+        .split(',')
+        .filter_map(|item| {
+            let item = item.trim();
+            if item.is_empty() {
+                return None;
+            }
+
+            let mut parts = item.split(';');
+            let coding = parts.next()?.trim().to_ascii_lowercase();
+
+            let q = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .next()
+                .and_then(|q| q.trim().parse().ok())
+                .unwrap_or(1.0);
+
+            Some((coding, q))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(accept_encoding: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_str(accept_encoding).unwrap());
+        headers
+    }
+
+    const CODECS: &[ContentEncoding] = &[
+        ContentEncoding::Br,
+        ContentEncoding::Zstd,
+        ContentEncoding::Gzip,
+        ContentEncoding::Deflate,
+    ];
+
+    #[test]
+    fn no_header_prefers_server_default() {
+        assert_eq!(
+            negotiate_content_encoding(&HeaderMap::new(), CODECS),
+            Some(ContentEncoding::Br),
+        );
+    }
+
+    #[test]
+    fn picks_highest_q_value() {
+        let headers = headers("gzip;q=0.5, br;q=0.2, deflate;q=1.0");
+        assert_eq!(
+            negotiate_content_encoding(&headers, CODECS),
+            Some(ContentEncoding::Deflate),
+        );
+    }
+
+    #[test]
+    fn ties_broken_by_server_preference_order() {
+        let headers = headers("gzip, br, zstd, deflate");
+        assert_eq!(
+            negotiate_content_encoding(&headers, CODECS),
+            Some(ContentEncoding::Br),
+        );
+    }
+
+    #[test]
+    fn drops_codecs_with_zero_q() {
+        let headers = headers("br;q=0, zstd;q=0, gzip;q=1.0");
+        assert_eq!(
+            negotiate_content_encoding(&headers, CODECS),
+            Some(ContentEncoding::Gzip),
+        );
+    }
+
+    #[test]
+    fn wildcard_covers_unlisted_codecs() {
+        let headers = headers("br;q=0, *;q=0.4");
+        assert_eq!(
+            negotiate_content_encoding(&headers, CODECS),
+            Some(ContentEncoding::Zstd),
+        );
+    }
+
+    #[test]
+    fn nothing_acceptable_returns_none() {
+        let headers = headers("identity;q=1.0, *;q=0");
+        assert_eq!(negotiate_content_encoding(&headers, CODECS), None);
+    }
+}
@@ -8,9 +8,10 @@ use std::{
 
 use actix_http::{body::AnyBody, header, Response, StatusCode};
 use bytes::BytesMut;
+use serde::Serialize;
 
 use crate::{__downcast_dyn, __downcast_get_type_id};
-use crate::{helpers, HttpResponse};
+use crate::{helpers, HttpRequest, HttpResponse};
 
 /// General purpose actix web error.
 ///
@@ -41,6 +42,21 @@ impl Error {
     pub fn error_response(&self) -> HttpResponse {
         self.cause.error_response()
     }
+
+    /// Returns the innermost error in this error's `source()` chain.
+    ///
+    /// Walks [`StdError::source`] as far as it goes. Returns `None` if the cause doesn't
+    /// implement `std::error::Error` (see [`ResponseError::as_std_error`]), in which case
+    /// there's no chain to walk at all.
+    pub fn root_cause(&self) -> Option<&(dyn StdError + 'static)> {
+        let mut current = self.source()?;
+
+        while let Some(next) = current.source() {
+            current = next;
+        }
+
+        Some(current)
+    }
 }
 
 impl fmt::Display for Error {
@@ -51,13 +67,21 @@ impl fmt::Display for Error {
 
 impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", &self.cause)
+        write!(f, "{:?}", &self.cause)?;
+
+        let mut source = self.cause.as_std_error().and_then(StdError::source);
+        while let Some(err) = source {
+            write!(f, "\n\nCaused by:\n    {}", err)?;
+            source = err.source();
+        }
+
+        Ok(())
     }
 }
 
 impl StdError for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+        self.cause.as_std_error()
     }
 }
 
@@ -107,6 +131,17 @@ pub trait ResponseError: fmt::Debug + fmt::Display {
         StatusCode::INTERNAL_SERVER_ERROR
     }
 
+    /// Returns this error as a `std::error::Error` trait object, if it implements one.
+    ///
+    /// [`Error`]'s `Debug` and `source()` impls use this to walk the full underlying error
+    /// chain (e.g. from a `?`-converted I/O or parse error) for logging and tracing.
+    /// Override this when `Self` implements [`std::error::Error`] — the default returns
+    /// `None` because `ResponseError` itself doesn't require that bound yet (see the TODO
+    /// above).
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        None
+    }
+
     /// Creates full response for error.
     ///
     /// By default, the generated response uses a 500 Internal Server Error status code, a
@@ -125,26 +160,278 @@ pub trait ResponseError: fmt::Debug + fmt::Display {
         res.set_body(AnyBody::from(buf))
     }
 
+    /// Creates full response for error, choosing its representation based on the request's
+    /// `Accept` header.
+    ///
+    /// Supports `text/plain` (the default, same body as [`error_response`](Self::error_response)),
+    /// `text/html` (a minimal styled error page), and `application/json`
+    /// (`{"error": "...", "status": N}`). Falls back to `error_response` when `req` has no
+    /// preference or asks for a representation that isn't one of those three. Unlike the
+    /// other methods on this trait, this one needs request access, so it's a separate hook
+    /// rather than a change to `error_response` itself.
+    fn error_response_for(&self, req: &HttpRequest) -> HttpResponse {
+        match accepted_error_representation(req) {
+            ErrorRepresentation::Json => self.error_response_json(),
+            ErrorRepresentation::Html => self.error_response_html(),
+            ErrorRepresentation::PlainText => self.error_response(),
+        }
+    }
+
+    /// Creates a response with a `{"error": "...", "status": N}` JSON body.
+    ///
+    /// Used by the default [`error_response_for`](Self::error_response_for) when the client
+    /// asks for `application/json`.
+    fn error_response_json(&self) -> HttpResponse {
+        #[derive(Serialize)]
+        struct JsonError<'a> {
+            error: &'a str,
+            status: u16,
+        }
+
+        let status = self.status_code();
+
+        let mut res = HttpResponse::new(status);
+
+        let mut buf = BytesMut::new();
+        let _ = serde_json::to_writer(
+            helpers::MutWriter(&mut buf),
+            &JsonError {
+                error: &self.to_string(),
+                status: status.as_u16(),
+            },
+        );
+
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json"),
+        );
+
+        res.set_body(AnyBody::from(buf))
+    }
+
+    /// Creates a response with a minimal styled `text/html` error page.
+    ///
+    /// Used by the default [`error_response_for`](Self::error_response_for) when the client
+    /// asks for `text/html`.
+    fn error_response_html(&self) -> HttpResponse {
+        let status = self.status_code();
+
+        let mut res = HttpResponse::new(status);
+
+        let message = escape_html(&self.to_string());
+
+        let mut buf = BytesMut::new();
+        let _ = write!(
+            helpers::MutWriter(&mut buf),
+            "<!DOCTYPE html>\
+             <html><head><title>{status}</title><style>\
+             body{{font-family:sans-serif;text-align:center;margin-top:10%}}\
+             </style></head>\
+             <body><h1>{status}</h1><p>{message}</p></body></html>",
+            status = status,
+            message = message,
+        );
+
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("text/html; charset=utf-8"),
+        );
+
+        res.set_body(AnyBody::from(buf))
+    }
+
+    /// Returns the [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807) Problem Details
+    /// representation of this error.
+    ///
+    /// The default fills `type` with `about:blank` and `title` with the status code's
+    /// reason phrase. Override this to set a more specific `type` URI or add extension
+    /// members, without having to reimplement [`error_response_problem_json`
+    /// ](Self::error_response_problem_json) as well.
+    fn problem_details(&self) -> ProblemDetails {
+        let status = self.status_code();
+
+        ProblemDetails {
+            type_uri: "about:blank".to_owned(),
+            title: status
+                .canonical_reason()
+                .unwrap_or("Unknown Error")
+                .to_owned(),
+            status: status.as_u16(),
+            detail: self.to_string(),
+            extensions: serde_json::Map::new(),
+        }
+    }
+
+    /// Creates a response using the `application/problem+json` media type, as opposed to
+    /// [`error_response`](Self::error_response)'s plain text body.
+    ///
+    /// This is opt-in: call it from your own `error_response` override (or from middleware
+    /// that negotiates on `Accept`) when API clients should get a machine-readable error.
+    fn error_response_problem_json(&self) -> HttpResponse {
+        let mut res = HttpResponse::new(self.status_code());
+
+        let mut buf = BytesMut::new();
+        let _ = serde_json::to_writer(helpers::MutWriter(&mut buf), &self.problem_details());
+
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/problem+json"),
+        );
+
+        res.set_body(AnyBody::from(buf))
+    }
+
     __downcast_get_type_id!();
 }
 
+/// [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807) Problem Details for HTTP APIs.
+///
+/// Returned by [`ResponseError::problem_details`] and serialized by
+/// [`ResponseError::error_response_problem_json`] as `application/problem+json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProblemDetails {
+    /// A URI reference that identifies the problem type. Defaults to `"about:blank"`.
+    #[serde(rename = "type")]
+    pub type_uri: String,
+
+    /// A short, human-readable summary of the problem type.
+    pub title: String,
+
+    /// The HTTP status code for this occurrence of the problem.
+    pub status: u16,
+
+    /// A human-readable explanation specific to this occurrence of the problem.
+    pub detail: String,
+
+    /// Additional, implementor-defined members, flattened into the top-level JSON object.
+    #[serde(flatten, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extensions: serde_json::Map<String, serde_json::Value>,
+}
+
 __downcast_dyn!(ResponseError);
 
-impl ResponseError for Box<dyn StdError + 'static> {}
+/// The error body representation negotiated from a request's `Accept` header by
+/// [`ResponseError::error_response_for`].
+enum ErrorRepresentation {
+    PlainText,
+    Html,
+    Json,
+}
+
+/// Picks the representation [`ResponseError::error_response_for`] should use, preferring
+/// `application/json` over `text/html` over the default `text/plain`.
+///
+/// Parses `Accept` the same q-value-aware way `actix_http`'s `Accept-Encoding` negotiation
+/// does: media types with `q=0` are excluded, and `*/*` is honored as a catch-all.
+fn accepted_error_representation(req: &HttpRequest) -> ErrorRepresentation {
+    let header = req
+        .headers()
+        .get(&header::ACCEPT)
+        .and_then(|val| val.to_str().ok());
+
+    let preferences = match header {
+        Some(header) if !header.is_empty() => parse_accept(header),
+        _ => return ErrorRepresentation::PlainText,
+    };
+
+    let wildcard_q = preferences
+        .iter()
+        .find(|(media_type, _)| media_type == "*/*")
+        .map(|&(_, q)| q);
+
+    let q_for = |media_type: &str| -> f32 {
+        preferences
+            .iter()
+            .find(|(candidate, _)| candidate == media_type)
+            .map(|&(_, q)| q)
+            .or(wildcard_q)
+            .unwrap_or(0.0)
+    };
+
+    let json_q = q_for("application/json");
+    let html_q = q_for("text/html");
+
+    if json_q > 0.0 && json_q >= html_q {
+        ErrorRepresentation::Json
+    } else if html_q > 0.0 {
+        ErrorRepresentation::Html
+    } else {
+        ErrorRepresentation::PlainText
+    }
+}
+
+/// Parses an `Accept` header value into `(media-type, q-value)` pairs, lower-casing media
+/// types and defaulting a missing `q` parameter to `1.0`.
+fn parse_accept(header: &str) -> Vec<(String, f32)> {
+    header
+        .split(',')
+        .filter_map(|item| {
+            let item = item.trim();
+            if item.is_empty() {
+                return None;
+            }
+
+            let mut parts = item.split(';');
+            let media_type = parts.next()?.trim().to_ascii_lowercase();
+
+            let q = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .next()
+                .and_then(|q| q.trim().parse().ok())
+                .unwrap_or(1.0);
+
+            Some((media_type, q))
+        })
+        .collect()
+}
+
+/// Escapes `&`, `<`, `>`, `"`, and `'` so untrusted text can be safely interpolated into an
+/// HTML response body.
+fn escape_html(input: &str) -> String {
+    input.chars().fold(String::with_capacity(input.len()), |mut out, c| {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#x27;"),
+            _ => out.push(c),
+        }
+        out
+    })
+}
+
+impl ResponseError for Box<dyn StdError + 'static> {
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self.as_ref())
+    }
+}
 
 #[cfg(feature = "openssl")]
-impl ResponseError for actix_tls::accept::openssl::SslError {}
+impl ResponseError for actix_tls::accept::openssl::SslError {
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
+}
 
 impl ResponseError for serde::de::value::Error {
     fn status_code(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
     }
+
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 impl ResponseError for std::str::Utf8Error {
     fn status_code(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
     }
+
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 impl ResponseError for std::io::Error {
@@ -156,9 +443,17 @@ impl ResponseError for std::io::Error {
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
+
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
-impl ResponseError for actix_http::error::HttpError {}
+impl ResponseError for actix_http::error::HttpError {
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
+}
 
 impl ResponseError for actix_http::Error {
     fn status_code(&self) -> StatusCode {
@@ -169,21 +464,37 @@ impl ResponseError for actix_http::Error {
     fn error_response(&self) -> HttpResponse {
         HttpResponse::new(self.status_code()).set_body(self.to_string().into())
     }
+
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 impl ResponseError for actix_http::header::InvalidHeaderValue {
     fn status_code(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
     }
+
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 impl ResponseError for actix_http::error::ParseError {
     fn status_code(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
     }
+
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
-impl ResponseError for actix_http::error::BlockingError {}
+impl ResponseError for actix_http::error::BlockingError {
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
+}
 
 impl ResponseError for actix_http::error::PayloadError {
     fn status_code(&self) -> StatusCode {
@@ -192,20 +503,36 @@ impl ResponseError for actix_http::error::PayloadError {
             _ => StatusCode::BAD_REQUEST,
         }
     }
+
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
-impl ResponseError for actix_http::ws::ProtocolError {}
+impl ResponseError for actix_http::ws::ProtocolError {
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
+}
 
 impl ResponseError for actix_http::error::ContentTypeError {
     fn status_code(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
     }
+
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 impl ResponseError for actix_http::ws::HandshakeError {
     fn error_response(&self) -> HttpResponse {
         Response::from(self).into()
     }
+
+    fn as_std_error(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self)
+    }
 }
 
 #[cfg(test)]